@@ -0,0 +1,121 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Default, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct Configuration {
+    pub logging: Logging,
+    pub multiplexer: Multiplexer,
+    pub metrics: Metrics,
+}
+
+#[derive(Default, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct Metrics {
+    // Prometheus scrape endpoint bind address, e.g. `0.0.0.0:9100`. Disabled
+    // when empty.
+    pub prometheus_bind: String,
+
+    // UDP info-query responder bind address, e.g. `0.0.0.0:1700`. Disabled when
+    // empty.
+    pub info_bind: String,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct Logging {
+    pub level: String,
+}
+
+impl Default for Logging {
+    fn default() -> Self {
+        Logging {
+            level: "info".into(),
+        }
+    }
+}
+
+#[derive(Default, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct Multiplexer {
+    pub bind: String,
+    pub servers: Vec<Server>,
+
+    // When set, the multiplexer listens in tunnel mode: datagrams received on
+    // `bind` are unwrapped from a ChaCha20-Poly1305 AEAD envelope before
+    // dispatch and downlinks are sealed before being sent back. This is the
+    // receiving end of the relay formed with a peer whose `Server.tunnel` uses
+    // the same passphrase.
+    pub tunnel: Option<Tunnel>,
+}
+
+#[derive(Default, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct Server {
+    pub server: String,
+    pub filters: Filters,
+
+    // Transport used to reach this server. The default plain UDP forwarding
+    // may silently drop packets on lossy links; `quic` tunnels the frames over
+    // a single congestion-controlled connection instead. Note that `quic`
+    // multiplexes every gateway onto one connection and so carries no
+    // per-gateway attribution for the return path: it is uplink-only, and a
+    // PULL_RESP arriving over it cannot be routed to a specific gateway.
+    pub transport: Transport,
+
+    // QUIC transport options, used when `transport` is `quic`.
+    pub quic: Quic,
+
+    // When set, datagrams exchanged with this server are wrapped in a
+    // ChaCha20-Poly1305 AEAD envelope so that two multiplexer instances can
+    // form a confidential, authenticated relay across an untrusted link. The
+    // peer must be configured with the same passphrase. Applies to the `udp`
+    // transport only; it is ignored when `transport` is `quic`.
+    pub tunnel: Option<Tunnel>,
+}
+
+#[derive(Default, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Transport {
+    #[default]
+    Udp,
+    Quic,
+}
+
+#[derive(Default, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct Quic {
+    // TLS server name presented by the remote bridge.
+    pub server_name: String,
+
+    // Optional path to a PEM-encoded CA certificate used to validate the
+    // remote bridge. When empty the platform roots are used.
+    pub ca_cert: String,
+}
+
+#[derive(Default, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct Tunnel {
+    pub passphrase: String,
+}
+
+#[derive(Default, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct Filters {
+    pub join_eui_prefixes: Vec<lrwn_filters::EuiPrefix>,
+    pub dev_addr_prefixes: Vec<lrwn_filters::DevAddrPrefix>,
+}
+
+impl From<&Filters> for lrwn_filters::Filters {
+    fn from(f: &Filters) -> Self {
+        lrwn_filters::Filters {
+            dev_addr_prefixes: f.dev_addr_prefixes.clone(),
+            join_eui_prefixes: f.join_eui_prefixes.clone(),
+        }
+    }
+}
+
+pub fn load(config_file: &str) -> anyhow::Result<Configuration> {
+    let content = std::fs::read_to_string(config_file)?;
+    let config: Configuration = toml::from_str(&content)?;
+    Ok(config)
+}
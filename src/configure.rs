@@ -0,0 +1,92 @@
+use std::io::{self, Write};
+use std::str::FromStr;
+
+use anyhow::Result;
+
+use chirpstack_packet_multiplexer::config::{Configuration, Filters, Multiplexer, Server};
+
+// run interactively prompts for the multiplexer bind address and each upstream
+// server with its JoinEUI and DevAddr prefix filters, validating every prefix
+// as it is entered, and writes a ready-to-use configuration file.
+pub fn run(config_file: &str) -> Result<()> {
+    let bind = prompt_default("Multiplexer bind address", "0.0.0.0:1700")?;
+
+    let mut servers = Vec::new();
+    loop {
+        let server = prompt("Upstream server address (blank to finish)")?;
+        if server.is_empty() {
+            break;
+        }
+
+        let join_eui_prefixes = prompt_prefixes::<lrwn_filters::EuiPrefix>(
+            "  JoinEUI prefix, e.g. 0200000000000000/8 (blank to finish)",
+        )?;
+        let dev_addr_prefixes = prompt_prefixes::<lrwn_filters::DevAddrPrefix>(
+            "  DevAddr prefix, e.g. 01000000/8 (blank to finish)",
+        )?;
+
+        servers.push(Server {
+            server,
+            filters: Filters {
+                join_eui_prefixes,
+                dev_addr_prefixes,
+            },
+            ..Default::default()
+        });
+    }
+
+    let conf = Configuration {
+        multiplexer: Multiplexer {
+            bind,
+            servers,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+
+    let out = toml::to_string_pretty(&conf)?;
+    std::fs::write(config_file, &out)?;
+    println!("Wrote configuration to {}", config_file);
+
+    Ok(())
+}
+
+// prompt_prefixes repeatedly reads prefix strings, validating each through the
+// lrwn_filters FromStr parser and re-prompting on error, until a blank line.
+fn prompt_prefixes<T>(label: &str) -> Result<Vec<T>>
+where
+    T: FromStr,
+    T::Err: std::fmt::Display,
+{
+    let mut prefixes = Vec::new();
+    loop {
+        let line = prompt(label)?;
+        if line.is_empty() {
+            break;
+        }
+
+        match T::from_str(&line) {
+            Ok(prefix) => prefixes.push(prefix),
+            Err(e) => println!("  Invalid prefix: {}", e),
+        }
+    }
+    Ok(prefixes)
+}
+
+fn prompt(label: &str) -> Result<String> {
+    print!("{}: ", label);
+    io::stdout().flush()?;
+
+    let mut line = String::new();
+    io::stdin().read_line(&mut line)?;
+    Ok(line.trim().to_string())
+}
+
+fn prompt_default(label: &str, default: &str) -> Result<String> {
+    let line = prompt(&format!("{} [{}]", label, default))?;
+    Ok(if line.is_empty() {
+        default.to_string()
+    } else {
+        line
+    })
+}
@@ -0,0 +1,425 @@
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use tokio::net::UdpSocket;
+use tokio::sync::{Mutex, mpsc};
+use tokio::task::JoinHandle;
+use tracing::{error, info, trace, warn};
+
+use crate::config::{Server, Transport};
+use crate::listener::{Downlink, Uplink};
+use crate::metrics::Metrics;
+use crate::packets::{GatewayId, Packet, PacketType, PushDataPayload};
+use crate::quic::QuicClient;
+use crate::tunnel::Tunnel;
+
+// How long a gateway association (EUI => socket address) is retained after the
+// last PULL_DATA / PUSH_DATA before it is considered stale. Gateways keep-alive
+// every ~10s, so this tolerates a couple of missed datagrams.
+const GATEWAY_EXPIRY: Duration = Duration::from_secs(30);
+
+// How long a pending downlink correlation (random token => issuing server) is
+// retained while waiting for the gateway's TX_ACK.
+const DOWNLINK_EXPIRY: Duration = Duration::from_secs(30);
+
+// State tracks the gateway associations and the in-flight downlink tokens so
+// that PULL_RESP / TX_ACK can be routed to the correct peer rather than
+// broadcast.
+#[derive(Default)]
+struct State {
+    // Gateway EUI => (last known socket address, last seen). Used to deliver
+    // downlinks to the owning gateway; PULL_RESP itself carries no EUI, so
+    // ownership is recovered from the transport.
+    gateways: HashMap<GatewayId, (SocketAddr, Instant)>,
+    // Random token => (issuing server index, issued at) for a PULL_RESP still
+    // awaiting its TX_ACK.
+    downlinks: HashMap<u16, (usize, Instant)>,
+}
+
+impl State {
+    fn track_gateway(&mut self, id: GatewayId, addr: SocketAddr) {
+        self.gateways.insert(id, (addr, Instant::now()));
+    }
+
+    // gateway_addr returns the last known socket address of a specific gateway,
+    // if it has not expired. Downlinks are routed to the owning gateway by EUI.
+    fn gateway_addr(&mut self, id: GatewayId) -> Option<SocketAddr> {
+        self.expire_gateways();
+        self.gateways.get(&id).map(|(addr, _)| *addr)
+    }
+
+    fn expire_gateways(&mut self) {
+        let now = Instant::now();
+        self.gateways
+            .retain(|_, (_, seen)| now.duration_since(*seen) < GATEWAY_EXPIRY);
+    }
+
+    fn track_downlink(&mut self, token: u16, server_index: usize) {
+        self.downlinks.insert(token, (server_index, Instant::now()));
+    }
+
+    // take_downlink resolves and removes the server index that issued the
+    // PULL_RESP matching this TX_ACK token, dropping it if it has expired.
+    fn take_downlink(&mut self, token: u16) -> Option<usize> {
+        let now = Instant::now();
+        self.downlinks
+            .retain(|_, (_, issued)| now.duration_since(*issued) < DOWNLINK_EXPIRY);
+        self.downlinks.remove(&token).map(|(index, _)| index)
+    }
+}
+
+// A frame received from an upstream, tagged with the gateway it belongs to when
+// the transport can attribute it (UDP per-gateway socket). QUIC multiplexes all
+// gateways onto one connection and so cannot, hence the Option.
+type Inbound = (Option<GatewayId>, Vec<u8>);
+
+struct ServerHandle {
+    config: Server,
+    outbound: Outbound,
+}
+
+// Outbound is the per-server transport used to reach an upstream: either UDP
+// datagrams (optionally tunnel-wrapped) with a dedicated source socket per
+// gateway, or a single long-lived QUIC connection shared by all gateways.
+enum Outbound {
+    Udp(Arc<UdpUpstream>),
+    Quic(QuicClient),
+}
+
+impl Outbound {
+    async fn send(&self, gateway_id: GatewayId, data: &[u8]) -> Result<()> {
+        match self {
+            Outbound::Udp(upstream) => upstream.send(gateway_id, data).await,
+            Outbound::Quic(client) => client.send(data).await,
+        }
+    }
+}
+
+// UdpUpstream forwards a server's traffic over a dedicated source socket per
+// gateway EUI. Because the upstream replies on the same socket it received a
+// gateway's PULL_DATA from, a PULL_RESP arriving on a given socket is known to
+// belong to that gateway, which is what enables ownership-based downlink
+// routing.
+struct UdpUpstream {
+    server: String,
+    tunnel: Option<Arc<Tunnel>>,
+    inbound_tx: mpsc::Sender<Inbound>,
+    sockets: Mutex<HashMap<GatewayId, GatewaySocket>>,
+}
+
+// GatewaySocket is a gateway's dedicated upstream source socket together with
+// the reader task demuxing its replies and the instant it was last used, so an
+// idle gateway's socket and task can be evicted rather than leaked forever.
+struct GatewaySocket {
+    sock: Arc<UdpSocket>,
+    reader: JoinHandle<()>,
+    last_used: Instant,
+}
+
+impl Drop for GatewaySocket {
+    fn drop(&mut self) {
+        self.reader.abort();
+    }
+}
+
+impl UdpUpstream {
+    async fn send(&self, gateway_id: GatewayId, data: &[u8]) -> Result<()> {
+        let sock = self.socket_for(gateway_id).await?;
+        match &self.tunnel {
+            Some(t) => sock.send(&t.seal(data)?).await?,
+            None => sock.send(data).await?,
+        };
+        Ok(())
+    }
+
+    // socket_for returns the gateway's dedicated upstream socket, creating and
+    // spawning a reader for it on first use. Expired entries are swept first so
+    // the map and its reader tasks stay bounded to currently-active gateways,
+    // mirroring the GATEWAY_EXPIRY applied to the association table.
+    async fn socket_for(&self, gateway_id: GatewayId) -> Result<Arc<UdpSocket>> {
+        let mut sockets = self.sockets.lock().await;
+
+        let now = Instant::now();
+        sockets.retain(|_, s| now.duration_since(s.last_used) < GATEWAY_EXPIRY);
+
+        if let Some(entry) = sockets.get_mut(&gateway_id) {
+            entry.last_used = now;
+            return Ok(entry.sock.clone());
+        }
+
+        let sock = Arc::new(UdpSocket::bind("0.0.0.0:0").await?);
+        sock.connect(&self.server).await?;
+
+        // Read datagrams from this gateway's socket, unwrap the tunnel envelope
+        // when enabled (dropping frames that fail authentication so injected
+        // packets never reach a gateway), and demux onto the inbound channel
+        // tagged with the owning gateway.
+        let read_sock = sock.clone();
+        let read_tunnel = self.tunnel.clone();
+        let read_inbound = self.inbound_tx.clone();
+        let reader = tokio::spawn(async move {
+            let mut buffer = [0u8; 65535];
+            loop {
+                match read_sock.recv(&mut buffer).await {
+                    Ok(size) => {
+                        let data = match &read_tunnel {
+                            Some(t) => match t.open(&buffer[..size]).await {
+                                Ok(v) => v,
+                                Err(e) => {
+                                    trace!(error = %e, "Dropping tunnel frame");
+                                    continue;
+                                }
+                            },
+                            None => buffer[..size].to_vec(),
+                        };
+                        if read_inbound.send((Some(gateway_id), data)).await.is_err() {
+                            return;
+                        }
+                    }
+                    Err(e) => error!(error = %e, "Reading from server socket error"),
+                }
+            }
+        });
+
+        sockets.insert(
+            gateway_id,
+            GatewaySocket {
+                sock: sock.clone(),
+                reader,
+                last_used: now,
+            },
+        );
+        Ok(sock)
+    }
+}
+
+// setup wires the uplink receiver to the configured upstream servers and routes
+// downlinks from those servers back to the owning gateways.
+pub async fn setup(
+    downlink_tx: mpsc::Sender<Downlink>,
+    mut uplink_rx: mpsc::Receiver<Uplink>,
+    servers: Vec<Server>,
+) -> Result<Arc<Metrics>> {
+    let state = Arc::new(Mutex::new(State::default()));
+    let metrics = Arc::new(Metrics::new(
+        servers.iter().map(|s| s.server.clone()).collect(),
+    ));
+
+    let mut server_handles = Vec::with_capacity(servers.len());
+    for (index, server) in servers.into_iter().enumerate() {
+        // Frames received from the server (demuxed out of the transport, tagged
+        // with the owning gateway when known) are fed onto this channel and
+        // routed back to a gateway.
+        let (inbound_tx, mut inbound_rx) = mpsc::channel::<Inbound>(100);
+
+        let outbound = match server.transport {
+            Transport::Udp => {
+                let tunnel = server
+                    .tunnel
+                    .as_ref()
+                    .map(|t| Arc::new(Tunnel::new(&t.passphrase)));
+
+                Outbound::Udp(Arc::new(UdpUpstream {
+                    server: server.server.clone(),
+                    tunnel,
+                    inbound_tx,
+                    sockets: Mutex::new(HashMap::new()),
+                }))
+            }
+            Transport::Quic => {
+                let addr = tokio::net::lookup_host(&server.server)
+                    .await?
+                    .next()
+                    .ok_or_else(|| anyhow::anyhow!("Resolving {} error", server.server))?;
+
+                // QUIC multiplexes all gateways onto one connection, so its
+                // inbound frames cannot be attributed to a gateway; adapt them
+                // onto the tagged channel with no owner.
+                let (quic_tx, mut quic_rx) = mpsc::channel::<Vec<u8>>(100);
+                tokio::spawn(async move {
+                    while let Some(data) = quic_rx.recv().await {
+                        if inbound_tx.send((None, data)).await.is_err() {
+                            return;
+                        }
+                    }
+                });
+
+                Outbound::Quic(QuicClient::new(addr, server.quic.clone(), quic_tx)?)
+            }
+        };
+
+        // Route inbound frames from this server back to the gateways.
+        let route_downlink_tx = downlink_tx.clone();
+        let route_state = state.clone();
+        let server_name = server.server.clone();
+        tokio::spawn(async move {
+            while let Some((gateway_id, data)) = inbound_rx.recv().await {
+                if let Err(e) =
+                    handle_downlink(&route_downlink_tx, &route_state, index, gateway_id, &data)
+                        .await
+                {
+                    error!(server = %server_name, error = %e, "Handling downlink error");
+                }
+            }
+        });
+
+        server_handles.push(ServerHandle {
+            config: server,
+            outbound,
+        });
+    }
+
+    let server_handles = Arc::new(server_handles);
+    let uplink_metrics = metrics.clone();
+    tokio::spawn(async move {
+        while let Some((addr, data)) = uplink_rx.recv().await {
+            if let Err(e) = handle_uplink(
+                &downlink_tx,
+                &server_handles,
+                &state,
+                &uplink_metrics,
+                addr,
+                data,
+            )
+            .await
+            {
+                error!(addr = %addr, error = %e, "Handling uplink error");
+            }
+        }
+    });
+
+    Ok(metrics)
+}
+
+// handle_downlink routes a datagram received from an upstream server. A
+// PULL_RESP is delivered to the gateway that owns it and its token is recorded
+// so the matching TX_ACK can be returned to this server only. PULL_RESP carries
+// no gateway EUI, so ownership is recovered from the transport: the UDP upstream
+// uses a dedicated source socket per gateway, so the socket a PULL_RESP arrived
+// on identifies its owner. The QUIC transport cannot attribute a gateway
+// (gateway_id is None), so it is uplink-only and an unattributable PULL_RESP is
+// dropped rather than broadcast to every radio.
+async fn handle_downlink(
+    downlink_tx: &mpsc::Sender<Downlink>,
+    state: &Arc<Mutex<State>>,
+    server_index: usize,
+    gateway_id: Option<GatewayId>,
+    data: &[u8],
+) -> Result<()> {
+    match Packet::from_slice(data)? {
+        Packet::PullResp(pull_resp) => {
+            trace!("Received PULL_RESP from server");
+            let mut state = state.lock().await;
+
+            // A PULL_RESP must reach the single gateway that owns it. Only the
+            // UDP upstream can attribute one (it replies on the per-gateway
+            // source socket the PULL_DATA arrived on); QUIC multiplexes every
+            // gateway onto one connection and carries no attribution, so it is
+            // uplink-only. Drop an unattributable PULL_RESP rather than
+            // broadcasting a single downlink onto every radio.
+            let target = match gateway_id {
+                Some(id) => state.gateway_addr(id),
+                None => {
+                    warn!(
+                        "Dropping PULL_RESP, transport cannot attribute a gateway (QUIC is uplink-only)"
+                    );
+                    None
+                }
+            };
+
+            match target {
+                Some(addr) => {
+                    // Record the correlation under the lock, then release it
+                    // before awaiting the send: the downlink channel is bounded,
+                    // and holding the State mutex across backpressure would
+                    // serialize all uplink processing behind it.
+                    state.track_downlink(pull_resp.random_token, server_index);
+                    drop(state);
+                    downlink_tx.send((addr, data.to_vec())).await?;
+                }
+                None => warn!("Dropping PULL_RESP, no active gateway association"),
+            }
+        }
+        // PUSH_ACK / PULL_ACK are acknowledged locally by the forwarder, so
+        // there is nothing to route back to a gateway.
+        _ => trace!("Ignoring non-PULL_RESP downlink packet"),
+    }
+
+    Ok(())
+}
+
+async fn handle_uplink(
+    downlink_tx: &mpsc::Sender<Downlink>,
+    server_handles: &[ServerHandle],
+    state: &Arc<Mutex<State>>,
+    metrics: &Arc<Metrics>,
+    addr: SocketAddr,
+    data: Vec<u8>,
+) -> Result<()> {
+    match Packet::from_slice(&data)? {
+        Packet::PushData(push_data) => {
+            trace!(addr = %addr, "Received PUSH_DATA");
+            state.lock().await.track_gateway(push_data.gateway_id, addr);
+            metrics.track_gateway(push_data.gateway_id);
+
+            // Acknowledge to the gateway.
+            downlink_tx
+                .send((addr, ack(push_data.protocol_version, push_data.random_token, PacketType::PushAck)))
+                .await?;
+
+            for (index, handle) in server_handles.iter().enumerate() {
+                let mut push_data = push_data.clone();
+                let mut payload: PushDataPayload = serde_json::from_slice(&push_data.payload)?;
+                payload.filter_rxpk(&(&handle.config.filters).into());
+                if payload.is_empty() {
+                    trace!(server = %handle.config.server, "Dropping PUSH_DATA, no rxpk left after filtering");
+                    metrics.incr_filtered(index, PacketType::PushData);
+                    continue;
+                }
+                push_data.payload = serde_json::to_vec(&payload)?;
+                handle.outbound.send(push_data.gateway_id, &push_data.to_bytes()?).await?;
+                metrics.incr_forwarded(index, PacketType::PushData);
+            }
+        }
+        Packet::PullData(pull_data) => {
+            trace!(addr = %addr, "Received PULL_DATA");
+            state.lock().await.track_gateway(pull_data.gateway_id, addr);
+            metrics.track_gateway(pull_data.gateway_id);
+
+            // Acknowledge to the gateway.
+            downlink_tx
+                .send((addr, ack(pull_data.protocol_version, pull_data.random_token, PacketType::PullAck)))
+                .await?;
+
+            for (index, handle) in server_handles.iter().enumerate() {
+                handle.outbound.send(pull_data.gateway_id, &data).await?;
+                metrics.incr_forwarded(index, PacketType::PullData);
+            }
+        }
+        Packet::TxAck(tx_ack) => {
+            // Return the TX_ACK only to the server that issued the matching
+            // PULL_RESP, correlated on the random token.
+            trace!(addr = %addr, "Received TX_ACK");
+            let server_index = state.lock().await.take_downlink(tx_ack.random_token);
+            match server_index.and_then(|i| server_handles.get(i)) {
+                Some(handle) => handle.outbound.send(tx_ack.gateway_id, &data).await?,
+                None => trace!(token = tx_ack.random_token, "Dropping TX_ACK, no matching downlink"),
+            }
+        }
+        _ => {
+            info!(addr = %addr, "Ignoring unexpected uplink packet-type");
+        }
+    }
+
+    Ok(())
+}
+
+// ack builds a bare acknowledgement frame (protocol version, random token,
+// packet-type) echoing the token of the packet being acknowledged.
+fn ack(protocol_version: u8, random_token: u16, packet_type: PacketType) -> Vec<u8> {
+    let [hi, lo] = random_token.to_be_bytes();
+    vec![protocol_version, hi, lo, packet_type.into()]
+}
@@ -0,0 +1,7 @@
+pub mod config;
+pub mod forwarder;
+pub mod listener;
+pub mod metrics;
+pub mod packets;
+pub mod quic;
+pub mod tunnel;
@@ -0,0 +1,88 @@
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use anyhow::Result;
+use tokio::net::UdpSocket;
+use tokio::sync::mpsc;
+use tracing::{error, trace};
+
+use crate::tunnel::Tunnel;
+
+// An uplink is a datagram received from a gateway, together with the gateway
+// socket address it originated from.
+pub type Uplink = (SocketAddr, Vec<u8>);
+
+// A downlink is a datagram to be sent back to a gateway socket address.
+pub type Downlink = (SocketAddr, Vec<u8>);
+
+// setup binds the gateway-facing UDP socket and returns a downlink sender (to
+// transmit datagrams back to the gateways) together with an uplink receiver
+// (yielding datagrams received from the gateways).
+pub async fn setup(bind: &str) -> Result<(mpsc::Sender<Downlink>, mpsc::Receiver<Uplink>)> {
+    setup_with_tunnel(bind, None).await
+}
+
+// setup_with_tunnel behaves like setup, but when a tunnel is given the socket
+// listens in tunnel mode: incoming datagrams are unwrapped from their AEAD
+// envelope before being dispatched (frames failing authentication are dropped)
+// and outgoing downlinks are sealed before transmission. This is the receiving
+// end of a relay formed with a peer multiplexer.
+pub async fn setup_with_tunnel(
+    bind: &str,
+    tunnel: Option<Arc<Tunnel>>,
+) -> Result<(mpsc::Sender<Downlink>, mpsc::Receiver<Uplink>)> {
+    let socket = Arc::new(UdpSocket::bind(bind).await?);
+
+    let (uplink_tx, uplink_rx) = mpsc::channel::<Uplink>(100);
+    let (downlink_tx, mut downlink_rx) = mpsc::channel::<Downlink>(100);
+
+    // Receive datagrams from the gateways.
+    let read_socket = socket.clone();
+    let read_tunnel = tunnel.clone();
+    tokio::spawn(async move {
+        let mut buffer = [0u8; 65535];
+        loop {
+            match read_socket.recv_from(&mut buffer).await {
+                Ok((size, addr)) => {
+                    let data = match &read_tunnel {
+                        Some(t) => match t.open(&buffer[..size]).await {
+                            Ok(v) => v,
+                            Err(e) => {
+                                trace!(addr = %addr, error = %e, "Dropping tunnel frame");
+                                continue;
+                            }
+                        },
+                        None => buffer[..size].to_vec(),
+                    };
+                    if uplink_tx.send((addr, data)).await.is_err() {
+                        return;
+                    }
+                }
+                Err(e) => error!(error = %e, "Reading from gateway socket error"),
+            }
+        }
+    });
+
+    // Send datagrams to the gateways.
+    let write_socket = socket.clone();
+    let write_tunnel = tunnel.clone();
+    tokio::spawn(async move {
+        while let Some((addr, data)) = downlink_rx.recv().await {
+            let data = match &write_tunnel {
+                Some(t) => match t.seal(&data) {
+                    Ok(v) => v,
+                    Err(e) => {
+                        error!(addr = %addr, error = %e, "Sealing tunnel frame error");
+                        continue;
+                    }
+                },
+                None => data,
+            };
+            if let Err(e) = write_socket.send_to(&data, addr).await {
+                error!(addr = %addr, error = %e, "Sending to gateway socket error");
+            }
+        }
+    });
+
+    Ok((downlink_tx, uplink_rx))
+}
@@ -0,0 +1,13 @@
+use anyhow::Result;
+use tracing_subscriber::{EnvFilter, prelude::*};
+
+pub fn setup(level: &str) -> Result<()> {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(level));
+
+    tracing_subscriber::registry()
+        .with(tracing_subscriber::fmt::layer())
+        .with(filter)
+        .init();
+
+    Ok(())
+}
@@ -0,0 +1,61 @@
+use anyhow::Result;
+use clap::{Parser, Subcommand};
+
+use std::sync::Arc;
+
+use chirpstack_packet_multiplexer::tunnel::Tunnel;
+use chirpstack_packet_multiplexer::{config, forwarder, listener, metrics};
+
+mod configure;
+mod logging;
+
+#[derive(Parser)]
+#[command(author, version, about)]
+struct Cli {
+    /// Path to the configuration file.
+    #[arg(short, long, default_value = "multiplexer.toml")]
+    config: String,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Interactively generate a configuration file.
+    Configure,
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let cli = Cli::parse();
+
+    if let Some(Command::Configure) = cli.command {
+        return configure::run(&cli.config);
+    }
+
+    let conf = config::load(&cli.config)?;
+
+    logging::setup(&conf.logging.level)?;
+
+    let tunnel = conf
+        .multiplexer
+        .tunnel
+        .as_ref()
+        .map(|t| Arc::new(Tunnel::new(&t.passphrase)));
+    let (downlink_tx, uplink_rx) =
+        listener::setup_with_tunnel(&conf.multiplexer.bind, tunnel).await?;
+    let metrics =
+        forwarder::setup(downlink_tx, uplink_rx, conf.multiplexer.servers.clone()).await?;
+
+    if !conf.metrics.prometheus_bind.is_empty() {
+        metrics::serve_prometheus(metrics.clone(), &conf.metrics.prometheus_bind).await?;
+    }
+    if !conf.metrics.info_bind.is_empty() {
+        metrics::serve_info(metrics.clone(), &conf.metrics.info_bind).await?;
+    }
+
+    tokio::signal::ctrl_c().await?;
+
+    Ok(())
+}
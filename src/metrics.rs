@@ -0,0 +1,194 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::Result;
+use serde_json::json;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, UdpSocket};
+use tracing::error;
+
+use crate::packets::{GatewayId, PacketType};
+
+// Magic query datagram that triggers a JSON snapshot response from the UDP
+// info responder.
+pub const INFO_QUERY: &[u8] = b"MUXINFO";
+
+// Metrics counts, per configured server, how many frames were forwarded to or
+// dropped by that server's filter, broken down by packet-type, and tracks the
+// gateway EUIs seen (from PULL_DATA / PUSH_DATA) with their last-seen
+// timestamps. Entries are never removed, so a gateway that has gone quiet is
+// still listed with its old timestamp.
+pub struct Metrics {
+    servers: Vec<ServerMetrics>,
+    gateways: Mutex<HashMap<GatewayId, SystemTime>>,
+}
+
+struct ServerMetrics {
+    name: String,
+    forwarded: Mutex<HashMap<String, u64>>,
+    filtered: Mutex<HashMap<String, u64>>,
+}
+
+impl Metrics {
+    pub fn new(server_names: Vec<String>) -> Self {
+        Metrics {
+            servers: server_names
+                .into_iter()
+                .map(|name| ServerMetrics {
+                    name,
+                    forwarded: Mutex::new(HashMap::new()),
+                    filtered: Mutex::new(HashMap::new()),
+                })
+                .collect(),
+            gateways: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn incr_forwarded(&self, server_index: usize, packet_type: PacketType) {
+        if let Some(server) = self.servers.get(server_index) {
+            *server
+                .forwarded
+                .lock()
+                .unwrap()
+                .entry(packet_type.to_string())
+                .or_default() += 1;
+        }
+    }
+
+    pub fn incr_filtered(&self, server_index: usize, packet_type: PacketType) {
+        if let Some(server) = self.servers.get(server_index) {
+            *server
+                .filtered
+                .lock()
+                .unwrap()
+                .entry(packet_type.to_string())
+                .or_default() += 1;
+        }
+    }
+
+    pub fn track_gateway(&self, id: GatewayId) {
+        self.gateways.lock().unwrap().insert(id, SystemTime::now());
+    }
+
+    // encode_prometheus renders the counters in the Prometheus text exposition
+    // format.
+    pub fn encode_prometheus(&self) -> String {
+        let mut out = String::new();
+
+        out.push_str("# HELP multiplexer_forwarded_total Frames forwarded to a server.\n");
+        out.push_str("# TYPE multiplexer_forwarded_total counter\n");
+        for server in &self.servers {
+            for (packet_type, count) in server.forwarded.lock().unwrap().iter() {
+                out.push_str(&format!(
+                    "multiplexer_forwarded_total{{server=\"{}\",packet_type=\"{}\"}} {}\n",
+                    server.name, packet_type, count
+                ));
+            }
+        }
+
+        out.push_str("# HELP multiplexer_filtered_total Frames dropped by a server's filter.\n");
+        out.push_str("# TYPE multiplexer_filtered_total counter\n");
+        for server in &self.servers {
+            for (packet_type, count) in server.filtered.lock().unwrap().iter() {
+                out.push_str(&format!(
+                    "multiplexer_filtered_total{{server=\"{}\",packet_type=\"{}\"}} {}\n",
+                    server.name, packet_type, count
+                ));
+            }
+        }
+
+        out.push_str("# HELP multiplexer_gateway_last_seen Unix timestamp a gateway was last seen.\n");
+        out.push_str("# TYPE multiplexer_gateway_last_seen gauge\n");
+        for (id, seen) in self.gateways.lock().unwrap().iter() {
+            out.push_str(&format!(
+                "multiplexer_gateway_last_seen{{gateway_id=\"{}\"}} {}\n",
+                id,
+                unix_secs(seen)
+            ));
+        }
+
+        out
+    }
+
+    // snapshot_json renders a JSON snapshot for the UDP info responder.
+    pub fn snapshot_json(&self) -> serde_json::Value {
+        let servers: Vec<_> = self
+            .servers
+            .iter()
+            .map(|server| {
+                json!({
+                    "server": server.name,
+                    "forwarded": *server.forwarded.lock().unwrap(),
+                    "filtered": *server.filtered.lock().unwrap(),
+                })
+            })
+            .collect();
+
+        let gateways: HashMap<String, u64> = self
+            .gateways
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(id, seen)| (id.to_string(), unix_secs(seen)))
+            .collect();
+
+        json!({ "servers": servers, "gateways": gateways })
+    }
+}
+
+fn unix_secs(t: &SystemTime) -> u64 {
+    t.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+// serve_prometheus answers HTTP scrapes with the Prometheus text exposition.
+pub async fn serve_prometheus(metrics: std::sync::Arc<Metrics>, bind: &str) -> Result<()> {
+    let listener = TcpListener::bind(bind).await?;
+    tokio::spawn(async move {
+        loop {
+            match listener.accept().await {
+                Ok((mut stream, _)) => {
+                    let metrics = metrics.clone();
+                    tokio::spawn(async move {
+                        // Drain the request line(s); the endpoint only serves
+                        // the metrics body regardless of the path.
+                        let mut buffer = [0u8; 1024];
+                        let _ = stream.read(&mut buffer).await;
+
+                        let body = metrics.encode_prometheus();
+                        let response = format!(
+                            "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\n\r\n{}",
+                            body.len(),
+                            body
+                        );
+                        let _ = stream.write_all(response.as_bytes()).await;
+                    });
+                }
+                Err(e) => error!(error = %e, "Accepting metrics connection error"),
+            }
+        }
+    });
+    Ok(())
+}
+
+// serve_info answers a magic UDP query datagram with a JSON snapshot, so a
+// headless multiplexer can be health-checked without shell access.
+pub async fn serve_info(metrics: std::sync::Arc<Metrics>, bind: &str) -> Result<()> {
+    let socket = UdpSocket::bind(bind).await?;
+    tokio::spawn(async move {
+        let mut buffer = [0u8; 64];
+        loop {
+            match socket.recv_from(&mut buffer).await {
+                Ok((size, addr)) if &buffer[..size] == INFO_QUERY => {
+                    let snapshot = metrics.snapshot_json().to_string();
+                    if let Err(e) = socket.send_to(snapshot.as_bytes(), addr).await {
+                        error!(error = %e, "Sending info snapshot error");
+                    }
+                }
+                Ok(_) => {}
+                Err(e) => error!(error = %e, "Reading info query error"),
+            }
+        }
+    });
+    Ok(())
+}
@@ -1,7 +1,9 @@
 use std::collections::HashMap;
 use std::fmt;
 
-use anyhow::{Result, anyhow};
+use anyhow::Result;
+use binrw::io::Cursor;
+use binrw::{BinRead, BinWrite, binrw, helpers::until_eof};
 use serde::{Deserialize, Serialize};
 
 #[derive(Clone, Copy, Debug)]
@@ -27,56 +29,17 @@ impl From<PacketType> for u8 {
     }
 }
 
-impl TryFrom<&[u8]> for PacketType {
-    type Error = anyhow::Error;
-
-    fn try_from(v: &[u8]) -> Result<PacketType> {
-        if v.len() < 4 {
-            return Err(anyhow!("At least 4 bytes are expected"));
-        }
-
-        Ok(match v[3] {
-            0x00 => PacketType::PushData,
-            0x01 => PacketType::PushAck,
-            0x02 => PacketType::PullData,
-            0x03 => PacketType::PullResp,
-            0x04 => PacketType::PullAck,
-            0x05 => PacketType::TxAck,
-            _ => return Err(anyhow!("Invalid packet-type: {}", v[3])),
-        })
-    }
-}
-
 impl fmt::Display for PacketType {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{:?}", self)
     }
 }
 
-#[derive(Clone, Copy, Debug)]
-pub enum ProtocolVersion {
-    Version1,
-    Version2,
-}
-
-impl TryFrom<&[u8]> for ProtocolVersion {
-    type Error = anyhow::Error;
-
-    fn try_from(v: &[u8]) -> Result<ProtocolVersion> {
-        if v.is_empty() {
-            return Err(anyhow!("At least 1 byte is expected"));
-        }
-
-        Ok(match v[0] {
-            0x01 => ProtocolVersion::Version1,
-            0x02 => ProtocolVersion::Version2,
-            _ => return Err(anyhow!("Unexpected protocol")),
-        })
-    }
-}
-
+// GatewayId is the 8-byte gateway EUI, stored big-endian as it appears on the
+// wire.
+#[binrw]
 #[derive(Clone, Copy, Eq, PartialEq, Hash, Debug)]
-pub struct GatewayId([u8; 8]);
+pub struct GatewayId(pub [u8; 8]);
 
 impl GatewayId {
     pub fn as_bytes_le(&self) -> [u8; 8] {
@@ -86,70 +49,152 @@ impl GatewayId {
     }
 }
 
-impl TryFrom<&[u8]> for GatewayId {
-    type Error = anyhow::Error;
-
-    fn try_from(v: &[u8]) -> Result<GatewayId> {
-        if v.len() < 12 {
-            return Err(anyhow!("At least 12 bytes are expected"));
-        }
-
-        let mut gateway_id: [u8; 8] = [0; 8];
-        gateway_id.copy_from_slice(&v[4..12]);
-        Ok(GatewayId(gateway_id))
-    }
-}
-
 impl fmt::Display for GatewayId {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{}", hex::encode(self.0))
     }
 }
 
-pub fn get_random_token(v: &[u8]) -> Result<u16> {
-    if v.len() < 3 {
-        return Err(anyhow!("At least 3 bytes are expected"));
+// Packet is the typed representation of any Semtech UDP datagram. Each variant
+// is distinguished by the packet-type byte that follows the protocol version
+// and big-endian random token, so the forwarder can match on it directly
+// instead of re-reading the raw bytes.
+#[binrw]
+#[brw(big)]
+#[derive(Clone, Debug)]
+pub enum Packet {
+    PushData(PushData),
+    PushAck(PushAck),
+    PullData(PullData),
+    PullResp(PullResp),
+    PullAck(PullAck),
+    TxAck(TxAck),
+}
+
+impl Packet {
+    pub fn from_slice(b: &[u8]) -> Result<Self> {
+        Ok(Packet::read(&mut Cursor::new(b))?)
     }
 
-    Ok(u16::from_be_bytes([v[1], v[2]]))
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let mut c = Cursor::new(Vec::new());
+        self.write(&mut c)?;
+        Ok(c.into_inner())
+    }
 }
 
+#[binrw]
+#[brw(big)]
+#[derive(Clone, Debug)]
 pub struct PushData {
     pub protocol_version: u8,
     pub random_token: u16,
-    pub gateway_id: [u8; 8],
-    pub payload: PushDataPayload,
+    #[brw(magic = 0x00u8)]
+    pub gateway_id: GatewayId,
+    #[br(parse_with = until_eof)]
+    pub payload: Vec<u8>,
 }
 
 impl PushData {
     pub fn from_slice(b: &[u8]) -> Result<Self> {
-        if b.len() < 14 {
-            return Err(anyhow!("At least 14 bytes are expected"));
-        }
+        Ok(PushData::read(&mut Cursor::new(b))?)
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let mut c = Cursor::new(Vec::new());
+        self.write(&mut c)?;
+        Ok(c.into_inner())
+    }
+}
 
-        Ok(PushData {
-            protocol_version: b[0],
-            random_token: u16::from_be_bytes([b[1], b[2]]),
-            gateway_id: {
-                let mut gateway_id: [u8; 8] = [0; 8];
-                gateway_id.copy_from_slice(&b[4..12]);
-                gateway_id
-            },
-            payload: serde_json::from_slice(&b[12..])?,
-        })
+#[binrw]
+#[brw(big)]
+#[derive(Clone, Debug)]
+pub struct PushAck {
+    pub protocol_version: u8,
+    pub random_token: u16,
+    #[br(temp, assert(packet_type == 0x01))]
+    #[bw(calc = 0x01u8)]
+    packet_type: u8,
+}
+
+#[binrw]
+#[brw(big)]
+#[derive(Clone, Debug)]
+pub struct PullData {
+    pub protocol_version: u8,
+    pub random_token: u16,
+    #[brw(magic = 0x02u8)]
+    pub gateway_id: GatewayId,
+}
+
+impl PullData {
+    pub fn from_slice(b: &[u8]) -> Result<Self> {
+        Ok(PullData::read(&mut Cursor::new(b))?)
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let mut c = Cursor::new(Vec::new());
+        self.write(&mut c)?;
+        Ok(c.into_inner())
+    }
+}
+
+#[binrw]
+#[brw(big)]
+#[derive(Clone, Debug)]
+pub struct PullResp {
+    pub protocol_version: u8,
+    pub random_token: u16,
+    #[brw(magic = 0x03u8)]
+    #[br(parse_with = until_eof)]
+    pub payload: Vec<u8>,
+}
+
+impl PullResp {
+    pub fn from_slice(b: &[u8]) -> Result<Self> {
+        Ok(PullResp::read(&mut Cursor::new(b))?)
     }
 
-    pub fn to_bytes(&self) -> Vec<u8> {
-        let mut b = vec![self.protocol_version];
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let mut c = Cursor::new(Vec::new());
+        self.write(&mut c)?;
+        Ok(c.into_inner())
+    }
+}
 
-        b.append(&mut self.random_token.to_be_bytes().to_vec());
-        b.push(0x00);
-        b.append(&mut self.gateway_id.to_vec());
+#[binrw]
+#[brw(big)]
+#[derive(Clone, Debug)]
+pub struct PullAck {
+    pub protocol_version: u8,
+    pub random_token: u16,
+    #[br(temp, assert(packet_type == 0x04))]
+    #[bw(calc = 0x04u8)]
+    packet_type: u8,
+}
 
-        let mut j = serde_json::to_vec(&self.payload).unwrap();
-        b.append(&mut j);
+#[binrw]
+#[brw(big)]
+#[derive(Clone, Debug)]
+pub struct TxAck {
+    pub protocol_version: u8,
+    pub random_token: u16,
+    #[brw(magic = 0x05u8)]
+    pub gateway_id: GatewayId,
+    #[br(parse_with = until_eof)]
+    pub payload: Vec<u8>,
+}
+
+impl TxAck {
+    pub fn from_slice(b: &[u8]) -> Result<Self> {
+        Ok(TxAck::read(&mut Cursor::new(b))?)
+    }
 
-        b
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let mut c = Cursor::new(Vec::new());
+        self.write(&mut c)?;
+        Ok(c.into_inner())
     }
 }
 
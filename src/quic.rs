@@ -0,0 +1,165 @@
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{Result, anyhow};
+use quinn::{ClientConfig, Endpoint};
+use tokio::sync::mpsc;
+use tokio::time::sleep;
+use tracing::{error, info, warn};
+
+use crate::config::Quic as QuicConfig;
+
+// Maximum time between reconnect attempts.
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+// QuicClient multiplexes all of a server's Semtech frames onto a single
+// long-lived QUIC connection. Outbound frames are queued on a channel that
+// survives reconnects, so a dropped session is re-established without losing
+// the gateway association state held by the forwarder.
+pub struct QuicClient {
+    tx: mpsc::Sender<Vec<u8>>,
+}
+
+impl QuicClient {
+    pub fn new(addr: SocketAddr, conf: QuicConfig, inbound: mpsc::Sender<Vec<u8>>) -> Result<Self> {
+        let client_config = build_client_config(&conf)?;
+
+        let mut endpoint = Endpoint::client("0.0.0.0:0".parse()?)?;
+        endpoint.set_default_client_config(client_config);
+
+        let (tx, rx) = mpsc::channel::<Vec<u8>>(100);
+        tokio::spawn(run(endpoint, addr, conf.server_name, rx, inbound));
+
+        Ok(QuicClient { tx })
+    }
+
+    // send queues a Semtech frame for transmission. It is buffered while the
+    // connection is re-establishing rather than dropped.
+    pub async fn send(&self, frame: &[u8]) -> Result<()> {
+        self.tx
+            .send(frame.to_vec())
+            .await
+            .map_err(|e| anyhow!("Queueing QUIC frame error: {}", e))
+    }
+}
+
+// run maintains the connection with exponential backoff, re-queuing the
+// outbound receiver across reconnects.
+async fn run(
+    endpoint: Endpoint,
+    addr: SocketAddr,
+    server_name: String,
+    mut rx: mpsc::Receiver<Vec<u8>>,
+    inbound: mpsc::Sender<Vec<u8>>,
+) {
+    let mut backoff = Duration::from_secs(1);
+
+    loop {
+        match connect(&endpoint, addr, &server_name, &mut rx, &inbound).await {
+            Ok(()) => return, // outbound channel closed, nothing left to do
+            Err(e) => {
+                warn!(addr = %addr, error = %e, backoff = ?backoff, "QUIC session error, reconnecting");
+                sleep(backoff).await;
+                backoff = (backoff * 2).min(MAX_BACKOFF);
+            }
+        }
+    }
+}
+
+async fn connect(
+    endpoint: &Endpoint,
+    addr: SocketAddr,
+    server_name: &str,
+    rx: &mut mpsc::Receiver<Vec<u8>>,
+    inbound: &mpsc::Sender<Vec<u8>>,
+) -> Result<()> {
+    let connection = endpoint.connect(addr, server_name)?.await?;
+    info!(addr = %addr, "QUIC connection established");
+
+    let (mut send_stream, mut recv_stream) = connection.open_bi().await?;
+
+    // Demux length-prefixed frames from the far end back into the forwarder.
+    let reader_inbound = inbound.clone();
+    let reader = tokio::spawn(async move {
+        loop {
+            let mut len = [0u8; 2];
+            if recv_stream.read_exact(&mut len).await.is_err() {
+                return;
+            }
+            let mut frame = vec![0u8; u16::from_be_bytes(len) as usize];
+            if recv_stream.read_exact(&mut frame).await.is_err() {
+                return;
+            }
+            if reader_inbound.send(frame).await.is_err() {
+                return;
+            }
+        }
+    });
+
+    // Frame each outbound Semtech packet with a big-endian length prefix, while
+    // watching for the peer closing the connection so a dropped session is
+    // detected even when there is no outbound traffic to fail on.
+    loop {
+        tokio::select! {
+            frame = rx.recv() => match frame {
+                Some(frame) => send_stream.write_all(&encode_frame(&frame)?).await?,
+                None => {
+                    // Outbound channel closed: nothing left to do.
+                    reader.abort();
+                    return Ok(());
+                }
+            },
+            reason = connection.closed() => {
+                reader.abort();
+                return Err(anyhow!("QUIC connection closed: {}", reason));
+            }
+        }
+    }
+}
+
+// encode_frame prefixes a Semtech frame with its big-endian u16 length for
+// transmission on the QUIC stream.
+fn encode_frame(frame: &[u8]) -> Result<Vec<u8>> {
+    let len = u16::try_from(frame.len()).map_err(|_| anyhow!("Frame too large for QUIC"))?;
+    let mut out = Vec::with_capacity(2 + frame.len());
+    out.extend_from_slice(&len.to_be_bytes());
+    out.extend_from_slice(frame);
+    Ok(out)
+}
+
+fn build_client_config(conf: &QuicConfig) -> Result<ClientConfig> {
+    let mut roots = rustls::RootCertStore::empty();
+    if conf.ca_cert.is_empty() {
+        for cert in rustls_native_certs::load_native_certs()? {
+            roots.add(cert)?;
+        }
+    } else {
+        let pem = std::fs::read(&conf.ca_cert)?;
+        for cert in rustls_pemfile::certs(&mut pem.as_slice()) {
+            roots.add(cert?)?;
+        }
+    }
+
+    ClientConfig::with_root_certificates(Arc::new(roots))
+        .map_err(|e| {
+            error!(error = %e, "Building QUIC client config error");
+            anyhow!("Building QUIC client config error: {}", e)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_frame() {
+        let encoded = encode_frame(&[0x02, 0x01, 0x02, 0x00]).unwrap();
+        assert_eq!(vec![0x00, 0x04, 0x02, 0x01, 0x02, 0x00], encoded);
+    }
+
+    #[test]
+    fn test_encode_frame_too_large() {
+        assert!(encode_frame(&vec![0u8; u16::MAX as usize + 1]).is_err());
+    }
+}
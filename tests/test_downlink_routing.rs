@@ -0,0 +1,95 @@
+use std::time::Duration;
+
+use tokio::net::UdpSocket;
+use tokio::time::timeout;
+use tracing_subscriber::prelude::*;
+
+use chirpstack_packet_multiplexer::{config, forwarder, listener};
+
+#[tokio::test]
+async fn test() {
+    tracing_subscriber::registry()
+        .with(tracing_subscriber::fmt::layer())
+        .init();
+
+    let conf = config::Configuration {
+        multiplexer: config::Multiplexer {
+            bind: "0.0.0.0:1712".into(),
+            servers: vec![config::Server {
+                server: "localhost:1713".into(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+
+    let (downlink_tx, uplink_rx) = listener::setup(&conf.multiplexer.bind).await.unwrap();
+    forwarder::setup(downlink_tx, uplink_rx, conf.multiplexer.servers.clone())
+        .await
+        .unwrap();
+    let mut buffer: [u8; 65535] = [0; 65535];
+
+    // Server socket.
+    let server_sock = UdpSocket::bind("0.0.0.0:1713").await.unwrap();
+
+    // Gateway socket.
+    let gw_sock = UdpSocket::bind("0.0.0.0:0").await.unwrap();
+    gw_sock.connect("localhost:1712").await.unwrap();
+
+    // Send PULL_DATA to establish the gateway association.
+    gw_sock
+        .send(&[
+            0x02, 0x00, 0x01, 0x02, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08,
+        ])
+        .await
+        .unwrap();
+
+    // Expect PULL_ACK.
+    let size = gw_sock.recv(&mut buffer).await.unwrap();
+    assert_eq!(&[0x02, 0x00, 0x01, 0x04], &buffer[..size]);
+
+    // Expect PULL_DATA forwarded to the server and learn the multiplexer's
+    // upstream socket address.
+    let (_, mux_addr) = server_sock.recv_from(&mut buffer).await.unwrap();
+
+    // Server issues a PULL_RESP (downlink) with random token 0x1234.
+    server_sock
+        .send_to(
+            &[
+                0x02, 0x12, 0x34, 0x03, 0x7b, 0x22, 0x74, 0x78, 0x70, 0x6b, 0x22, 0x3a, 0x7b, 0x7d,
+                0x7d,
+            ],
+            mux_addr,
+        )
+        .await
+        .unwrap();
+
+    // Expect PULL_RESP routed to the gateway that owns the association.
+    let size = gw_sock.recv(&mut buffer).await.unwrap();
+    assert_eq!(
+        &[
+            0x02, 0x12, 0x34, 0x03, 0x7b, 0x22, 0x74, 0x78, 0x70, 0x6b, 0x22, 0x3a, 0x7b, 0x7d,
+            0x7d,
+        ],
+        &buffer[..size]
+    );
+
+    // Gateway replies with a TX_ACK carrying the same token.
+    gw_sock
+        .send(&[
+            0x02, 0x12, 0x34, 0x05, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x7b, 0x7d,
+        ])
+        .await
+        .unwrap();
+
+    // Expect the TX_ACK returned only to the server that issued the PULL_RESP.
+    let resp = timeout(Duration::from_millis(500), server_sock.recv(&mut buffer)).await;
+    let size = resp.unwrap().unwrap();
+    assert_eq!(
+        &[
+            0x02, 0x12, 0x34, 0x05, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x7b, 0x7d,
+        ],
+        &buffer[..size]
+    );
+}
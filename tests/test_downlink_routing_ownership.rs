@@ -0,0 +1,101 @@
+use std::time::Duration;
+
+use tokio::net::UdpSocket;
+use tokio::time::timeout;
+use tracing_subscriber::prelude::*;
+
+use chirpstack_packet_multiplexer::{config, forwarder, listener};
+
+// With more than one gateway associated, a PULL_RESP must reach only the
+// gateway that owns it. The UDP upstream uses a dedicated source socket per
+// gateway, so the socket the server received a gateway's PULL_DATA on is the
+// socket it must answer on for the downlink to be attributed correctly.
+#[tokio::test]
+async fn test() {
+    tracing_subscriber::registry()
+        .with(tracing_subscriber::fmt::layer())
+        .init();
+
+    let conf = config::Configuration {
+        multiplexer: config::Multiplexer {
+            bind: "0.0.0.0:1716".into(),
+            servers: vec![config::Server {
+                server: "localhost:1717".into(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+
+    let (downlink_tx, uplink_rx) = listener::setup(&conf.multiplexer.bind).await.unwrap();
+    forwarder::setup(downlink_tx, uplink_rx, conf.multiplexer.servers.clone())
+        .await
+        .unwrap();
+    let mut buffer: [u8; 65535] = [0; 65535];
+
+    // Server socket.
+    let server_sock = UdpSocket::bind("0.0.0.0:1717").await.unwrap();
+
+    // Two gateways with distinct EUIs.
+    let gw_a = UdpSocket::bind("0.0.0.0:0").await.unwrap();
+    gw_a.connect("localhost:1716").await.unwrap();
+    let gw_b = UdpSocket::bind("0.0.0.0:0").await.unwrap();
+    gw_b.connect("localhost:1716").await.unwrap();
+
+    // Gateway A: EUI 0102030405060708.
+    gw_a.send(&[
+        0x02, 0x00, 0x01, 0x02, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08,
+    ])
+    .await
+    .unwrap();
+    let size = gw_a.recv(&mut buffer).await.unwrap();
+    assert_eq!(&[0x02, 0x00, 0x01, 0x04], &buffer[..size]);
+
+    // Gateway B: EUI 1112131415161718.
+    gw_b.send(&[
+        0x02, 0x00, 0x02, 0x02, 0x11, 0x12, 0x13, 0x14, 0x15, 0x16, 0x17, 0x18,
+    ])
+    .await
+    .unwrap();
+    let size = gw_b.recv(&mut buffer).await.unwrap();
+    assert_eq!(&[0x02, 0x00, 0x02, 0x04], &buffer[..size]);
+
+    // The server sees both PULL_DATA datagrams, each on a distinct upstream
+    // socket; find the one that carries gateway A's EUI.
+    let mut addr_a = None;
+    for _ in 0..2 {
+        let (size, from) = server_sock.recv_from(&mut buffer).await.unwrap();
+        if buffer[4..12] == [0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08] {
+            addr_a = Some(from);
+        }
+        assert_eq!(size, 12);
+    }
+    let addr_a = addr_a.expect("gateway A PULL_DATA not received by server");
+
+    // Server issues a PULL_RESP on gateway A's socket only.
+    server_sock
+        .send_to(
+            &[
+                0x02, 0x12, 0x34, 0x03, 0x7b, 0x22, 0x74, 0x78, 0x70, 0x6b, 0x22, 0x3a, 0x7b, 0x7d,
+                0x7d,
+            ],
+            addr_a,
+        )
+        .await
+        .unwrap();
+
+    // Gateway A receives the downlink.
+    let size = gw_a.recv(&mut buffer).await.unwrap();
+    assert_eq!(
+        &[
+            0x02, 0x12, 0x34, 0x03, 0x7b, 0x22, 0x74, 0x78, 0x70, 0x6b, 0x22, 0x3a, 0x7b, 0x7d,
+            0x7d,
+        ],
+        &buffer[..size]
+    );
+
+    // Gateway B must not receive it.
+    let resp = timeout(Duration::from_millis(200), gw_b.recv(&mut buffer)).await;
+    assert!(resp.is_err(), "downlink leaked to the wrong gateway");
+}
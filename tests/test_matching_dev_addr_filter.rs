@@ -24,6 +24,7 @@ async fn test() {
                 },
                 ..Default::default()
             }],
+            ..Default::default()
         },
         ..Default::default()
     };
@@ -26,6 +26,7 @@ async fn test() {
                 },
                 ..Default::default()
             }],
+            ..Default::default()
         },
         ..Default::default()
     };
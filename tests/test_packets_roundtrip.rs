@@ -0,0 +1,98 @@
+use chirpstack_packet_multiplexer::packets::Packet;
+
+// Feed a representative frame of every packet-type through decode => encode and
+// assert the bytes come back identical.
+#[test]
+fn test_roundtrip() {
+    let frames: Vec<Vec<u8>> = vec![
+        // PUSH_DATA: version, token, 0x00, gateway EUI, JSON tail.
+        vec![
+            0x02, 0x01, 0x02, 0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x7b, 0x22,
+            0x72, 0x78, 0x70, 0x6b, 0x22, 0x3a, 0x5b, 0x5d, 0x7d,
+        ],
+        // PUSH_ACK: version, token, 0x01.
+        vec![0x02, 0x01, 0x02, 0x01],
+        // PULL_DATA: version, token, 0x02, gateway EUI.
+        vec![
+            0x02, 0x0a, 0x0b, 0x02, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08,
+        ],
+        // PULL_RESP: version, token, 0x03, JSON tail.
+        vec![
+            0x02, 0x0c, 0x0d, 0x03, 0x7b, 0x22, 0x74, 0x78, 0x70, 0x6b, 0x22, 0x3a, 0x7b, 0x7d,
+            0x7d,
+        ],
+        // PULL_ACK: version, token, 0x04.
+        vec![0x02, 0x0e, 0x0f, 0x04],
+        // TX_ACK: version, token, 0x05, gateway EUI, JSON tail.
+        vec![
+            0x02, 0x10, 0x11, 0x05, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x7b, 0x7d,
+        ],
+    ];
+
+    for frame in frames {
+        let packet = Packet::from_slice(&frame).unwrap();
+        assert_eq!(frame, packet.to_bytes().unwrap());
+    }
+}
+
+// A tiny deterministic PRNG so the property test varies its inputs without
+// pulling in a dependency.
+struct XorShift(u64);
+
+impl XorShift {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+}
+
+// Feed randomly generated valid frames of every JSON-bearing type through
+// decode => encode and assert they round-trip byte-for-byte, varying the
+// token, gateway EUI and tail body length/content (random bytes, which the
+// codec carries as an opaque payload).
+#[test]
+fn test_roundtrip_random() {
+    let mut rng = XorShift(0x9e3779b97f4a7c15);
+
+    for _ in 0..1000 {
+        let [hi, lo] = (rng.next() as u16).to_be_bytes();
+        let eui: Vec<u8> = (0..8).map(|_| rng.next() as u8).collect();
+        let body: Vec<u8> = (0..(rng.next() as usize % 64)).map(|_| rng.next() as u8).collect();
+
+        // PUSH_DATA: version, token, 0x00, EUI, body.
+        let mut push_data = vec![0x02, hi, lo, 0x00];
+        push_data.extend_from_slice(&eui);
+        push_data.extend_from_slice(&body);
+
+        // PULL_RESP: version, token, 0x03, body.
+        let mut pull_resp = vec![0x02, hi, lo, 0x03];
+        pull_resp.extend_from_slice(&body);
+
+        // TX_ACK: version, token, 0x05, EUI, body.
+        let mut tx_ack = vec![0x02, hi, lo, 0x05];
+        tx_ack.extend_from_slice(&eui);
+        tx_ack.extend_from_slice(&body);
+
+        for frame in [push_data, pull_resp, tx_ack] {
+            let packet = Packet::from_slice(&frame).unwrap();
+            assert_eq!(frame, packet.to_bytes().unwrap());
+        }
+    }
+}
+
+// Vary the random token across the full u16 range to exercise the big-endian
+// token field of the bare acknowledgement types (PUSH_ACK, PULL_ACK).
+#[test]
+fn test_roundtrip_tokens() {
+    for token in (0u16..=u16::MAX).step_by(97) {
+        let [hi, lo] = token.to_be_bytes();
+
+        for type_byte in [0x01u8, 0x04u8] {
+            let frame = vec![0x02, hi, lo, type_byte];
+            let packet = Packet::from_slice(&frame).unwrap();
+            assert_eq!(frame, packet.to_bytes().unwrap());
+        }
+    }
+}
@@ -0,0 +1,72 @@
+use std::str::FromStr;
+use std::sync::Arc;
+
+use tokio::net::UdpSocket;
+use tracing_subscriber::prelude::*;
+
+use chirpstack_packet_multiplexer::tunnel::Tunnel;
+use chirpstack_packet_multiplexer::{config, forwarder, listener};
+
+// Exercises the receiving end of the relay: a peer multiplexer listening in
+// tunnel mode must decrypt sealed datagrams before dispatch and forward the
+// plaintext to its configured server.
+#[tokio::test]
+async fn test() {
+    tracing_subscriber::registry()
+        .with(tracing_subscriber::fmt::layer())
+        .init();
+
+    let conf = config::Configuration {
+        multiplexer: config::Multiplexer {
+            bind: "0.0.0.0:1714".into(),
+            tunnel: Some(config::Tunnel {
+                passphrase: "secret".into(),
+            }),
+            servers: vec![config::Server {
+                server: "localhost:1715".into(),
+                filters: config::Filters {
+                    dev_addr_prefixes: vec![
+                        lrwn_filters::DevAddrPrefix::from_str("01000000/8").unwrap(),
+                    ],
+                    ..Default::default()
+                },
+                ..Default::default()
+            }],
+        },
+        ..Default::default()
+    };
+
+    let tunnel = Arc::new(Tunnel::new("secret"));
+    let (downlink_tx, uplink_rx) =
+        listener::setup_with_tunnel(&conf.multiplexer.bind, Some(tunnel.clone()))
+            .await
+            .unwrap();
+    forwarder::setup(downlink_tx, uplink_rx, conf.multiplexer.servers.clone())
+        .await
+        .unwrap();
+    let mut buffer: [u8; 65535] = [0; 65535];
+
+    // Server socket.
+    let server_sock = UdpSocket::bind("0.0.0.0:1715").await.unwrap();
+
+    // Peer socket (the sending multiplexer instance).
+    let peer_sock = UdpSocket::bind("0.0.0.0:0").await.unwrap();
+    peer_sock.connect("localhost:1714").await.unwrap();
+
+    // Seal a PUSH_DATA (DevAddr 01020304) and send it through the tunnel.
+    let push_data = [
+        0x02, 0x01, 0x02, 0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x7b, 0x22, 0x72,
+        0x78, 0x70, 0x6b, 0x22, 0x3a, 0x5b, 0x7b, 0x22, 0x64, 0x61, 0x74, 0x61, 0x22, 0x3a, 0x22,
+        0x51, 0x41, 0x51, 0x44, 0x41, 0x67, 0x45, 0x3d, 0x22, 0x7d, 0x5d, 0x7d,
+    ];
+    peer_sock.send(&tunnel.seal(&push_data).unwrap()).await.unwrap();
+
+    // Expect the decrypted PUSH_DATA forwarded to the server in cleartext.
+    let size = server_sock.recv(&mut buffer).await.unwrap();
+    assert_eq!(&push_data, &buffer[..size]);
+
+    // Expect a sealed PUSH_ACK back to the peer, which unwraps to the ack.
+    let size = peer_sock.recv(&mut buffer).await.unwrap();
+    let ack = tunnel.open(&buffer[..size]).await.unwrap();
+    assert_eq!(&[0x02, 0x01, 0x02, 0x01], ack.as_slice());
+}